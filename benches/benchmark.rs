@@ -1,84 +1,274 @@
-use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BatchSize, BenchmarkGroup, BenchmarkId,
+    Criterion, Throughput,
+};
+#[cfg(feature = "blake3-rayon")]
+use order_insensitive_compare::blake3_par_concat;
+#[cfg(feature = "simd")]
+use order_insensitive_compare::compare_digests_simd;
 use order_insensitive_compare::{
-    ahash_par, ahash_seq, blake3_par, blake3_seq, eq_by_ahash_par, eq_by_ahash_seq,
-    eq_by_blake3_par, eq_by_blake3_seq, eq_by_sha256_par, eq_by_sha256_seq, eq_by_sorting_par,
-    eq_by_sorting_seq, sha256_par, sha256_seq,
+    ahash_par, ahash_seq, ahash_seq_with_sort, blake3_merkle_par, blake3_par, blake3_seq,
+    eq_by_ahash_par, eq_by_ahash_par_with, eq_by_ahash_seq, eq_by_ahash_seq_buf,
+    eq_by_ahash_streaming, eq_by_blake3_par, eq_by_blake3_seq, eq_by_sha256_par, eq_by_sha256_seq,
+    eq_by_sha256_trunc_seq, eq_by_sorting_par, eq_by_sorting_seq, eq_by_xxh3_par, eq_by_xxh3_seq,
+    sha256_par, sha256_seq, sha256_tree_par, xxh3_par, xxh3_seq, EntriesBuf, ParallelConfig,
+    SortStrategy,
 };
+#[cfg(feature = "blake2")]
+use order_insensitive_compare::{blake2b_par, blake2b_seq};
+#[cfg(feature = "wyhash")]
+use order_insensitive_compare::{wyhash_par, wyhash_seq};
 use rand::prelude::*;
 use rayon::prelude::*;
 
-pub fn criterion_benchmark(c: &mut Criterion) {
-    const FILE_SIZE: usize = 60 * 1024 * 1024;
-    const NUM_ENTRIES: usize = 1000;
-    const ENTRY_SIZE: usize = FILE_SIZE / NUM_ENTRIES;
+// Input classes of the same total size (60 MiB), but with different tradeoffs
+// between the number of entries and their size. Hashing cost is proportional
+// to the total size, whereas sorting cost grows with the number of entries, so
+// this shows where the crossover between backends lies.
+const INPUT_CLASSES: [(usize, usize); 3] =
+    [(10, 6 * 1024 * 1024), (1000, 60 * 1024), (1024 * 1024, 60)];
 
-    let mut data = vec![vec![0; ENTRY_SIZE]; NUM_ENTRIES];
+fn random_entries(num_entries: usize, entry_size: usize) -> Vec<Vec<u8>> {
+    let mut data = vec![vec![0; entry_size]; num_entries];
     data.par_iter_mut().for_each(|entry| {
         let mut rng = rand::thread_rng();
         rng.fill_bytes(&mut entry[..]);
     });
+    data
+}
 
-    c.bench_function("seq ahash", |b| {
-        b.iter_batched(
-            || data.clone(),
-            |data| ahash_seq(data),
-            criterion::BatchSize::LargeInput,
-        );
+fn bench_hash<R>(
+    group: &mut BenchmarkGroup<WallTime>,
+    id: BenchmarkId,
+    data: &[Vec<u8>],
+    hash: fn(Vec<Vec<u8>>) -> R,
+) {
+    group.bench_with_input(id, data, |b, data| {
+        b.iter_batched(|| data.to_vec(), hash, BatchSize::LargeInput);
     });
+}
 
-    c.bench_function("seq sha256", |b| {
-        b.iter_batched(
-            || data.clone(),
-            |data| sha256_seq(data),
-            criterion::BatchSize::LargeInput,
-        );
-    });
+pub fn criterion_benchmark(c: &mut Criterion) {
+    for &(num_entries, entry_size) in &INPUT_CLASSES {
+        let data = random_entries(num_entries, entry_size);
+        let mut shuffled = data.clone();
+        shuffled.shuffle(&mut rand::thread_rng());
 
-    c.bench_function("seq blake3", |b| {
-        b.iter_batched(
-            || data.clone(),
-            |data| blake3_seq(data),
-            criterion::BatchSize::LargeInput,
-        );
-    });
+        // Results are grouped by input class, so that all backends can be
+        // compared on the same input
+        let mut group = c.benchmark_group(format!("{} entries of {} B", num_entries, entry_size));
+        group.throughput(Throughput::Bytes((num_entries * entry_size) as u64));
 
-    c.bench_function("par ahash", |b| {
-        b.iter_batched(
-            || data.clone(),
-            |data| ahash_par(data),
-            criterion::BatchSize::LargeInput,
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("seq", "ahash"),
+            &data,
+            ahash_seq,
         );
-    });
+        bench_hash(&mut group, BenchmarkId::new("seq", "xxh3"), &data, xxh3_seq);
+        #[cfg(feature = "wyhash")]
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("seq", "wyhash"),
+            &data,
+            wyhash_seq,
+        );
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("seq", "sha256"),
+            &data,
+            sha256_seq,
+        );
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("seq", "blake3"),
+            &data,
+            blake3_seq,
+        );
+        #[cfg(feature = "blake2")]
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("seq", "blake2b"),
+            &data,
+            blake2b_seq,
+        );
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("par", "ahash"),
+            &data,
+            ahash_par,
+        );
+        bench_hash(&mut group, BenchmarkId::new("par", "xxh3"), &data, xxh3_par);
+        #[cfg(feature = "wyhash")]
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("par", "wyhash"),
+            &data,
+            wyhash_par,
+        );
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("par", "sha256"),
+            &data,
+            sha256_par,
+        );
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("par", "sha256 tree"),
+            &data,
+            sha256_tree_par,
+        );
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("par", "blake3"),
+            &data,
+            blake3_par,
+        );
+        #[cfg(feature = "blake2")]
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("par", "blake2b"),
+            &data,
+            blake2b_par,
+        );
+        bench_hash(
+            &mut group,
+            BenchmarkId::new("par", "blake3 merkle"),
+            &data,
+            blake3_merkle_par,
+        );
+
+        for &(mode, compares) in &[
+            (
+                "seq compare",
+                [
+                    ("sorting", eq_by_sorting_seq as fn(_, _) -> bool),
+                    ("ahash", eq_by_ahash_seq),
+                    ("xxh3", eq_by_xxh3_seq),
+                    ("sha256", eq_by_sha256_seq),
+                    ("blake3", eq_by_blake3_seq),
+                ],
+            ),
+            (
+                "par compare",
+                [
+                    ("sorting", eq_by_sorting_par as fn(_, _) -> bool),
+                    ("ahash", eq_by_ahash_par),
+                    ("xxh3", eq_by_xxh3_par),
+                    ("sha256", eq_by_sha256_par),
+                    ("blake3", eq_by_blake3_par),
+                ],
+            ),
+        ] {
+            for &(backend, eq) in &compares {
+                group.bench_with_input(
+                    BenchmarkId::new(mode, backend),
+                    &(&data, &shuffled),
+                    |b, (data, shuffled)| {
+                        b.iter_batched(
+                            || (data.to_vec(), shuffled.to_vec()),
+                            |(data, shuffled)| eq(data, shuffled),
+                            BatchSize::LargeInput,
+                        );
+                    },
+                );
+            }
+        }
+        group.finish();
+    }
+
+    // The remaining benchmarks focus on specific scenarios, and only use the
+    // middle input class
+    let (num_entries, entry_size) = INPUT_CLASSES[1];
+    let data = random_entries(num_entries, entry_size);
+    let mut shuffled = data.clone();
+    shuffled.shuffle(&mut rand::thread_rng());
+
+    // Unequal inputs which only differ by one entry, as a worst case for
+    // hash-based comparisons that do not exit early
+    let mut unequal = shuffled.clone();
+    unequal[0][0] ^= 1;
 
-    c.bench_function("par sha256", |b| {
+    c.bench_function("seq unequal compare via ahash", |b| {
         b.iter_batched(
-            || data.clone(),
-            |data| sha256_par(data),
-            criterion::BatchSize::LargeInput,
+            || (data.clone(), unequal.clone()),
+            |(data, unequal)| eq_by_ahash_seq(data, unequal),
+            BatchSize::LargeInput,
         );
     });
 
-    c.bench_function("par blake3", |b| {
+    c.bench_function("seq unequal compare via ahash streaming", |b| {
         b.iter_batched(
-            || data.clone(),
-            |data| blake3_par(data),
-            criterion::BatchSize::LargeInput,
+            || (data.clone(), unequal.clone()),
+            |(data, unequal)| eq_by_ahash_streaming(data, unequal),
+            BatchSize::LargeInput,
         );
     });
 
+    // Inputs which only differ by one missing entry, which hash-based
+    // comparisons can reject without hashing anything
+    let mut truncated = shuffled.clone();
+    truncated.pop();
+
+    for &(name, eq) in &[
+        ("ahash", eq_by_ahash_seq as fn(_, _) -> bool),
+        ("sha256", eq_by_sha256_seq),
+        ("blake3", eq_by_blake3_seq),
+    ] {
+        c.bench_function(&format!("seq different length compare via {}", name), |b| {
+            b.iter_batched(
+                || (data.clone(), truncated.clone()),
+                |(data, truncated)| eq(data, truncated),
+                BatchSize::LargeInput,
+            );
+        });
+    }
+}
+
+pub fn sort_strategy_benchmark(c: &mut Criterion) {
+    // Small entries, so that sorting the hashes takes a significant fraction
+    // of the run time
+    let mut rng = rand::thread_rng();
+    for &num_entries in &[1_000, 100_000, 1_000_000] {
+        let data = (0..num_entries)
+            .map(|_| rng.gen::<u64>().to_le_bytes().to_vec())
+            .collect::<Vec<_>>();
+        for &(name, sort) in &[
+            ("comparison", SortStrategy::Comparison),
+            ("radix", SortStrategy::Radix),
+        ] {
+            c.bench_function(&format!("seq ahash {} sort {}", name, num_entries), |b| {
+                b.iter_batched(
+                    || data.clone(),
+                    |data| ahash_seq_with_sort(data, sort),
+                    BatchSize::LargeInput,
+                );
+            });
+        }
+    }
+}
+
+pub fn entries_buf_benchmark(c: &mut Criterion) {
+    const NUM_ENTRIES: usize = 1_000_000;
+    const ENTRY_SIZE: usize = 16;
+
     let mut rng = rand::thread_rng();
+    let data = (0..NUM_ENTRIES)
+        .map(|_| {
+            let mut entry = vec![0; ENTRY_SIZE];
+            rng.fill_bytes(&mut entry[..]);
+            entry
+        })
+        .collect::<Vec<_>>();
     let mut shuffled = data.clone();
     shuffled.shuffle(&mut rng);
 
-    c.bench_function("seq compare via sorting", |b| {
-        b.iter_batched(
-            || (data.clone(), shuffled.clone()),
-            |(data, shuffled)| eq_by_sorting_seq(data, shuffled),
-            BatchSize::LargeInput,
-        );
+    // Many tiny entries, so that per-entry overheads like hasher setup matter
+    c.bench_function("seq hash small entries via ahash", |b| {
+        b.iter_batched(|| data.clone(), ahash_seq, BatchSize::LargeInput);
     });
 
-    c.bench_function("seq compare via ahash", |b| {
+    c.bench_function("seq compare small entries via ahash", |b| {
         b.iter_batched(
             || (data.clone(), shuffled.clone()),
             |(data, shuffled)| eq_by_ahash_seq(data, shuffled),
@@ -86,54 +276,137 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         );
     });
 
-    c.bench_function("seq compare via sha256", |b| {
-        b.iter_batched(
-            || (data.clone(), shuffled.clone()),
-            |(data, shuffled)| eq_by_sha256_seq(data, shuffled),
-            BatchSize::LargeInput,
-        );
+    // With many small entries, the sequential final fold of blake3_par is a
+    // larger fraction of the total work
+    c.bench_function("par blake3 small entries", |b| {
+        b.iter_batched(|| data.clone(), blake3_par, BatchSize::LargeInput);
     });
 
-    c.bench_function("seq compare via blake3", |b| {
-        b.iter_batched(
-            || (data.clone(), shuffled.clone()),
-            |(data, shuffled)| eq_by_blake3_seq(data, shuffled),
-            BatchSize::LargeInput,
-        );
+    c.bench_function("par blake3 merkle small entries", |b| {
+        b.iter_batched(|| data.clone(), blake3_merkle_par, BatchSize::LargeInput);
     });
 
-    c.bench_function("par compare via sorting", |b| {
-        b.iter_batched(
-            || (data.clone(), shuffled.clone()),
-            |(data, shuffled)| eq_by_sorting_par(data, shuffled),
-            BatchSize::LargeInput,
-        );
+    #[cfg(feature = "blake3-rayon")]
+    c.bench_function("par blake3 concat small entries", |b| {
+        b.iter_batched(|| data.clone(), blake3_par_concat, BatchSize::LargeInput);
     });
 
-    c.bench_function("par compare via ahash", |b| {
+    let data_buf = data.iter().collect::<EntriesBuf>();
+    let shuffled_buf = shuffled.iter().collect::<EntriesBuf>();
+    c.bench_function("seq compare small entries via ahash buf", |b| {
         b.iter_batched(
-            || (data.clone(), shuffled.clone()),
-            |(data, shuffled)| eq_by_ahash_par(data, shuffled),
+            || (data_buf.clone(), shuffled_buf.clone()),
+            |(data, shuffled)| eq_by_ahash_seq_buf(data, shuffled),
             BatchSize::LargeInput,
         );
     });
+}
 
-    c.bench_function("par compare via sha256", |b| {
+// Inputs which are too small for parallelism to pay off. eq_by_ahash_par should
+// now be as fast as eq_by_ahash_seq there, as it falls back to it below the
+// parallel threshold, whereas forcing the parallel path shows rayon's overhead.
+pub fn small_input_benchmark(c: &mut Criterion) {
+    let data = random_entries(100, 64);
+    let mut shuffled = data.clone();
+    shuffled.shuffle(&mut rand::thread_rng());
+
+    let mut group = c.benchmark_group("100 entries of 64 B");
+    let forced_par = ParallelConfig {
+        min_parallel_len: 0,
+        pool: None,
+    };
+    for &(mode, eq) in &[
+        ("seq compare", eq_by_ahash_seq as fn(_, _) -> bool),
+        ("par compare", eq_by_ahash_par),
+    ] {
+        group.bench_function(BenchmarkId::new(mode, "ahash"), |b| {
+            b.iter_batched(
+                || (data.clone(), shuffled.clone()),
+                |(data, shuffled)| eq(data, shuffled),
+                BatchSize::SmallInput,
+            );
+        });
+    }
+    group.bench_function(BenchmarkId::new("forced par compare", "ahash"), |b| {
         b.iter_batched(
             || (data.clone(), shuffled.clone()),
-            |(data, shuffled)| eq_by_sha256_par(data, shuffled),
-            BatchSize::LargeInput,
+            |(data, shuffled)| eq_by_ahash_par_with(data, shuffled, &forced_par),
+            BatchSize::SmallInput,
         );
     });
+    group.finish();
+}
+
+// Truncated sha256 digests are cheaper to compare than full ones, but have to
+// be sorted as records of a runtime size rather than as fixed-size arrays.
+// Many small entries, so that sorting the digests takes a significant fraction
+// of the run time.
+pub fn truncated_digest_benchmark(c: &mut Criterion) {
+    let data = random_entries(1_000_000, 16);
+    let mut shuffled = data.clone();
+    shuffled.shuffle(&mut rand::thread_rng());
 
-    c.bench_function("par compare via blake3", |b| {
+    let mut group = c.benchmark_group("1000000 entries of 16 B");
+    group.bench_function(BenchmarkId::new("seq compare", "sha256"), |b| {
         b.iter_batched(
             || (data.clone(), shuffled.clone()),
-            |(data, shuffled)| eq_by_blake3_par(data, shuffled),
+            |(data, shuffled)| eq_by_sha256_seq(data, shuffled),
             BatchSize::LargeInput,
         );
     });
+    for &bytes in &[8, 16, 32] {
+        group.bench_function(
+            BenchmarkId::new("seq compare", format!("sha256 truncated to {} B", bytes)),
+            |b| {
+                b.iter_batched(
+                    || (data.clone(), shuffled.clone()),
+                    |(data, shuffled)| eq_by_sha256_trunc_seq(data, shuffled, bytes),
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+// Comparison of sorted digest lists, which is the final step of the blake3 and
+// sha256 equality checks
+#[cfg(feature = "simd")]
+pub fn compare_digests_benchmark(c: &mut Criterion) {
+    const NUM_DIGESTS: usize = 1_000_000;
+
+    let mut rng = rand::thread_rng();
+    let mut digests = (0..NUM_DIGESTS)
+        .map(|_| {
+            let mut digest = [0; 32];
+            rng.fill_bytes(&mut digest[..]);
+            digest
+        })
+        .collect::<Vec<[u8; 32]>>();
+    digests.sort_unstable();
+    let copy = digests.clone();
+
+    c.bench_function("compare digests scalar", |b| {
+        b.iter(|| criterion::black_box(&digests) == criterion::black_box(&copy));
+    });
+
+    c.bench_function("compare digests simd", |b| {
+        b.iter(|| {
+            compare_digests_simd(criterion::black_box(&digests), criterion::black_box(&copy))
+        });
+    });
 }
+//
+#[cfg(not(feature = "simd"))]
+pub fn compare_digests_benchmark(_c: &mut Criterion) {}
 
-criterion_group!(benches, criterion_benchmark);
+criterion_group!(
+    benches,
+    criterion_benchmark,
+    sort_strategy_benchmark,
+    entries_buf_benchmark,
+    small_input_benchmark,
+    truncated_digest_benchmark,
+    compare_digests_benchmark
+);
 criterion_main!(benches);
@@ -0,0 +1,18 @@
+// Computes the blake3 fingerprint of a fixed list of entries, using only the
+// blake3 feature:
+//
+//     cargo run --example blake3_fixed --no-default-features --features blake3
+//
+// This feature set does not pull in rayon, so the library also builds for
+// targets where rayon does not work, like WebAssembly:
+//
+//     cargo build --lib --target wasm32-unknown-unknown \
+//         --no-default-features --features blake3
+use order_insensitive_compare::{blake3_seq, eq_by_blake3_seq};
+
+fn main() {
+    let entries = vec!["foo", "bar", "baz"];
+    let shuffled = vec!["baz", "foo", "bar"];
+    assert!(eq_by_blake3_seq(entries.clone(), shuffled));
+    println!("{}", blake3_seq(entries));
+}